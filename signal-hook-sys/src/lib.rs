@@ -10,13 +10,29 @@ pub mod internal {
 
     // Careful: make sure the signature and the constants match the C source
     extern "C" {
-        fn sighook_signal_origin(info: *const siginfo_t, pid: *mut pid_t, uid: *mut uid_t) -> u8;
+        fn sighook_signal_origin(
+            info: *const siginfo_t,
+            pid: *mut pid_t,
+            uid: *mut uid_t,
+            cause: *mut u8,
+        ) -> u8;
     }
 
     const ORIGIN_UNKNOWN: u8 = 0;
     const ORIGIN_PROCESS: u8 = 1;
     const ORIGIN_KERNEL: u8 = 2;
 
+    // Careful: make sure these match the CAUSE_* constants in the C source.
+    const CAUSE_USER: u8 = 0;
+    const CAUSE_QUEUE: u8 = 1;
+    const CAUSE_MESGQ: u8 = 2;
+    const CAUSE_EXITED: u8 = 3;
+    const CAUSE_KILLED: u8 = 4;
+    const CAUSE_DUMPED: u8 = 5;
+    const CAUSE_TRAPPED: u8 = 6;
+    const CAUSE_STOPPED: u8 = 7;
+    const CAUSE_CONTINUED: u8 = 8;
+
     #[derive(Clone, Debug, Eq, PartialEq)]
     #[non_exhaustive]
     pub enum Cause {
@@ -31,6 +47,23 @@ pub mod internal {
         Continued,
     }
 
+    impl Cause {
+        fn from_raw(raw: u8) -> Self {
+            match raw {
+                CAUSE_USER => Cause::User,
+                CAUSE_QUEUE => Cause::Queue,
+                CAUSE_MESGQ => Cause::MesgQ,
+                CAUSE_EXITED => Cause::Exited,
+                CAUSE_KILLED => Cause::Killed,
+                CAUSE_DUMPED => Cause::Dumped,
+                CAUSE_TRAPPED => Cause::Trapped,
+                CAUSE_STOPPED => Cause::Stopped,
+                CAUSE_CONTINUED => Cause::Continued,
+                _ => unsafe { abort() }, // Not unreachable. Not async-signal-safe.
+            }
+        }
+    }
+
     #[derive(Clone, Debug, Eq, PartialEq)]
     #[non_exhaustive]
     pub enum Origin {
@@ -47,12 +80,16 @@ pub mod internal {
         pub fn extract(info: &siginfo_t) -> Self {
             let mut pid: pid_t = 0;
             let mut uid: uid_t = 0;
-            let origin = unsafe { sighook_signal_origin(info, &mut pid, &mut uid) };
+            let mut cause: u8 = 0;
+            let origin = unsafe { sighook_signal_origin(info, &mut pid, &mut uid, &mut cause) };
             match origin {
                 ORIGIN_UNKNOWN => Origin::Unknown,
                 ORIGIN_KERNEL => Origin::Kernel,
-                // TODO
-                ORIGIN_PROCESS => Origin::Process { pid, uid, cause: Cause::User },
+                ORIGIN_PROCESS => Origin::Process {
+                    pid,
+                    uid,
+                    cause: Cause::from_raw(cause),
+                },
                 _ => unsafe { abort() }, // Not unreachable. Not async-signal-safe.
             }
         }
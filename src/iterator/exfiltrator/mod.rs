@@ -0,0 +1,423 @@
+#![allow(missing_docs)]
+//! An abstraction over exfiltrating information out of signal handlers.
+//!
+//! The [`Exfiltrator`] trait provides a way to abstract the information extracted from a signal
+//! handler and the way it is extracted out of it.
+//!
+//! The implementations can be used to parametrize the
+//! [`SignalsInfo`][crate::iterator::SignalsInfo] to specify what results are returned.
+//!
+//! Besides the exfiltrators shipped here, [`Exfiltrator`] is implementable by downstream crates
+//! too. The [`ring`] module provides a small lock-free buffer that's handy for a [`Storage`][
+//! Exfiltrator::Storage] that needs to hold onto more than one value between `load`s.
+
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use libc::{c_int, pid_t, siginfo_t, uid_t};
+use signal_hook_consts::SI_QUEUE;
+use signal_hook_sys::internal;
+
+pub mod ring;
+
+/// A trait describing what and how is extracted from signal handlers.
+///
+/// By choosing a specific implementor as the type parameter for
+/// [`SignalsInfo`][crate::iterator::SignalsInfo], one can pick how much and what information is
+/// returned from the iterator.
+///
+/// # Safety
+///
+/// [`store`][Exfiltrator::store] is called from inside the signal handler and must be
+/// async-signal-safe: no locking, no allocation, and tolerant of being interrupted by (or
+/// interrupting) a concurrent call to itself or to [`load`][Exfiltrator::load]. Implementing this
+/// correctly may be difficult, therefore care needs to be taken. One method known to work is
+/// encoding the data into an atomic variable (see the implementations in this module for
+/// examples); the [`ring`] module provides a ready-made building block for buffering more than a
+/// single value this way.
+pub unsafe trait Exfiltrator: Debug + Send + Sync + 'static {
+    /// One slot for storing the data.
+    ///
+    /// Each signal will get its one slot of this type, independent of other signals. It can
+    /// store the information in there inside the signal handler and will be loaded from it in
+    /// load.
+    ///
+    /// Each slot is initialized to the [`Default`] value. It is expected this value represents
+    /// „no signal delivered“ state.
+    type Storage: Debug + Default + Send + Sync + 'static;
+
+    /// The type returned to the user.
+    type Output;
+
+    /// If the given signal is supported by this specific exfiltrator.
+    ///
+    /// Not all information is available to all signals, therefore not all exfiltrators must
+    /// support all signals. If `false` is returned, the user is prevented for registering such
+    /// signal number with the given exfiltrator.
+    fn supports_signal(&self, sig: c_int) -> bool;
+
+    /// Puts the signal information inside the slot.
+    ///
+    /// It needs to somehow store the relevant information and the fact that a signal happened.
+    ///
+    /// # Warning
+    ///
+    /// This will be called inside the signal handler. It needs to be async-signal-safe. In
+    /// particular, very small amount of operations are allowed in there. This namely does
+    /// *not* include any locking nor allocation.
+    ///
+    /// It is also possible that multiple store methods are called concurrently; it is up to
+    /// the implementor to deal with that.
+    fn store(&self, slot: &Self::Storage, signal: c_int, info: &siginfo_t);
+
+    /// Loads the signal information from the given slot.
+    ///
+    /// The method shall check if the signal happened (it may be possible to be called without
+    /// the signal previously being delivered; it is up to the implementer to recognize it). It
+    /// is assumed the [`Default`] value is recognized as no signal delivered.
+    ///
+    /// If it was delivered, the method shall extract the relevant information *and reset the
+    /// slot* to the no signal delivered state.
+    ///
+    /// It shall return `Some(value)` if the signal was successfully received and `None` in
+    /// case no signal was delivered.
+    ///
+    /// No blocking shall happen inside this method. It may be called concurrently with
+    /// [`store`][Exfiltrator::store] (due to how signals work, concurrently even inside the
+    /// same thread ‒ a `store` may „interrupt“ a call to `load`). It is up to the implementer
+    /// to deal with that.
+    fn load(&self, slot: &Self::Storage, signal: c_int) -> Option<Self::Output>;
+}
+
+/// An [`Exfiltrator`] providing just the signal numbers.
+///
+/// This is the basic exfiltrator for most needs. For that reason, there's the
+/// [`crate::iterator::Signals`] type alias, to simplify the type names for usual needs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SignalOnly;
+
+unsafe impl Exfiltrator for SignalOnly {
+    type Storage = AtomicBool;
+    fn supports_signal(&self, _: c_int) -> bool {
+        true
+    }
+    type Output = c_int;
+
+    fn store(&self, slot: &Self::Storage, _: c_int, _: &siginfo_t) {
+        slot.store(true, Ordering::SeqCst);
+    }
+
+    fn load(&self, slot: &Self::Storage, signal: c_int) -> Option<Self::Output> {
+        if slot
+            .compare_exchange(true, false, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+        {
+            Some(signal)
+        } else {
+            None
+        }
+    }
+}
+
+/// The detailed cause of a signal, as reported by the kernel.
+///
+/// This mirrors [`signal_hook_sys::internal::Cause`], which is where the actual `siginfo_t`
+/// interpretation happens; this public copy exists so [`WithOrigin`] can hand it out without
+/// exposing the internal, unstable crate.
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Cause {
+    User,
+    Queue,
+    MesgQ,
+    Exited,
+    Killed,
+    Dumped,
+    Trapped,
+    Stopped,
+    Continued,
+}
+
+impl Cause {
+    const fn to_raw(&self) -> u8 {
+        match self {
+            Cause::User => 0,
+            Cause::Queue => 1,
+            Cause::MesgQ => 2,
+            Cause::Exited => 3,
+            Cause::Killed => 4,
+            Cause::Dumped => 5,
+            Cause::Trapped => 6,
+            Cause::Stopped => 7,
+            Cause::Continued => 8,
+        }
+    }
+
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            0 => Cause::User,
+            1 => Cause::Queue,
+            2 => Cause::MesgQ,
+            3 => Cause::Exited,
+            4 => Cause::Killed,
+            5 => Cause::Dumped,
+            6 => Cause::Trapped,
+            7 => Cause::Stopped,
+            8 => Cause::Continued,
+            _ => unreachable!("raw cause is always produced by Cause::to_raw"),
+        }
+    }
+}
+
+impl From<internal::Cause> for Cause {
+    fn from(cause: internal::Cause) -> Self {
+        match cause {
+            internal::Cause::User => Cause::User,
+            internal::Cause::Queue => Cause::Queue,
+            internal::Cause::MesgQ => Cause::MesgQ,
+            internal::Cause::Exited => Cause::Exited,
+            internal::Cause::Killed => Cause::Killed,
+            internal::Cause::Dumped => Cause::Dumped,
+            internal::Cause::Trapped => Cause::Trapped,
+            internal::Cause::Stopped => Cause::Stopped,
+            internal::Cause::Continued => Cause::Continued,
+            // `internal::Cause` is `#[non_exhaustive]`; signal-hook-sys guarantees it only ever
+            // hands back the variants above.
+            _ => unreachable!("unknown signal_hook_sys::internal::Cause variant"),
+        }
+    }
+}
+
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum OriginType {
+    Unknown,
+    Process {
+        pid: pid_t,
+        uid: uid_t,
+        cause: Cause,
+    }
+}
+
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Origin {
+    pub signal: c_int,
+    pub origin_type: OriginType,
+}
+
+impl Origin {
+    /// The [`Signal`][crate::low_level::Signal] this origin was recorded for, if it is one of
+    /// the well-known signals.
+    ///
+    /// [`signal`][Origin::signal] stays the canonical `c_int` field (it covers unknown and
+    /// real-time signal numbers too); this is a convenience for matching on a name instead of a
+    /// number.
+    pub fn signal_name(&self) -> Option<crate::low_level::Signal> {
+        crate::low_level::Signal::try_from(self.signal).ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct OriginStorage(AtomicU64);
+
+impl Default for OriginStorage {
+    fn default() -> Self {
+        OriginStorage(AtomicU64::new(WithOrigin::EMPTY))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WithOrigin;
+
+impl WithOrigin {
+    // `pid` and `uid` are `c_int`-sized, but a real PID never gets anywhere near the top of that
+    // range (Linux caps it well under 2^28), so the cause ‒ which only ever needs a handful of
+    // values ‒ is packed into the spare high bits of the *same* word instead of a separate
+    // atomic. That way a single `store`/`swap` moves cause, pid and uid together, and two
+    // concurrent `store` calls can never have their cause and pid/uid torn apart.
+    const PID_BITS: u32 = 28;
+    const PID_MASK: u64 = (1 << Self::PID_BITS) - 1;
+    const CAUSE_SHIFT: u32 = 64 - 4;
+
+    const fn compose(pid: pid_t, uid: uid_t, cause: u8) -> u64 {
+        let pid = ((pid as u32) as u64) & Self::PID_MASK;
+        let uid = (uid as u32) as u64;
+        let cause = (cause as u64) << Self::CAUSE_SHIFT;
+        cause | (pid << 32) | uid
+    }
+    // An impossible PID (the 28-bit field can never legitimately be all-ones) marks a sentinel;
+    // `EMPTY` and `UNKNOWN` are told apart by the uid that comes along with it, same as before.
+    const EMPTY: u64 = Self::compose(-1, 1, 0);
+    const UNKNOWN: u64 = Self::compose(-1, 2, 0);
+}
+
+unsafe impl Exfiltrator for WithOrigin {
+    type Storage = OriginStorage;
+    type Output = Origin;
+
+    fn supports_signal(&self, _: c_int) -> bool {
+        true
+    }
+
+    fn store(&self, slot: &Self::Storage, _: c_int, info: &siginfo_t) {
+        let composed = match internal::Origin::extract(info) {
+            internal::Origin::Process { pid, uid, cause } => {
+                Self::compose(pid, uid, Cause::from(cause).to_raw())
+            }
+            // `internal::Origin` is `#[non_exhaustive]`, so catch any future variant the same
+            // way as the ones we know about today.
+            _ => Self::UNKNOWN,
+        };
+        slot.0.store(composed, Ordering::SeqCst);
+    }
+
+    fn load(&self, slot: &Self::Storage, signal: c_int) -> Option<Self::Output> {
+        let loaded = slot.0.swap(Self::EMPTY, Ordering::SeqCst);
+        match loaded {
+            Self::EMPTY => None,
+            Self::UNKNOWN => {
+                Some(Origin {
+                    signal,
+                    origin_type: OriginType::Unknown,
+                })
+            },
+            composed => {
+                let pid = (((composed >> 32) & Self::PID_MASK) as u32) as pid_t;
+                let uid = (composed as u32) as uid_t;
+                let cause = Cause::from_raw((composed >> Self::CAUSE_SHIFT) as u8);
+                Some(Origin {
+                    signal,
+                    origin_type: OriginType::Process {
+                        pid,
+                        uid,
+                        cause,
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// The `sigval` payload attached to a signal delivered through `sigqueue(3)`.
+///
+/// `value` is `None` when the signal was delivered some other way (e.g. a plain `kill`/`raise`,
+/// or any `si_code` this crate doesn't specifically recognize) ‒ the signal still happened, it
+/// just didn't come with a queued value.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SigInfoValue {
+    pub signal: c_int,
+    pub value: Option<c_int>,
+}
+
+/// An [`Exfiltrator`] capturing the `sigval` payload sent through `sigqueue(3)`.
+///
+/// This lets two processes (or a signal handler and the rest of the program) pass a small
+/// integer alongside the signal itself, without setting up any other IPC. Only signals where the
+/// kernel actually guarantees a meaningful `si_value` ‒ the real-time signals ‒ are
+/// [`supported`][Exfiltrator::supports_signal]; for anything else registering will fail.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WithRawSiginfo;
+
+impl WithRawSiginfo {
+    // Three distinct states are encoded here, not two: `EMPTY` (no signal delivered yet),
+    // `DELIVERED_NO_VALUE` (a signal came in, but not via `sigqueue(3)`, so there's no payload),
+    // and a composed value (bit 33 tags "value present", so it can never collide with either of
+    // the other two sentinels, no matter which `c_int` was queued).
+    const EMPTY: u64 = 0;
+    const DELIVERED_NO_VALUE: u64 = 1 << 32;
+    const PRESENT: u64 = 1 << 33;
+
+    const fn compose(value: c_int) -> u64 {
+        Self::PRESENT | ((value as u32) as u64)
+    }
+}
+
+// `sigval` is a union of `sival_int` (a `c_int`) and `sival_ptr` (a pointer); both members start
+// at the union's base address. libc only exposes the `sival_ptr` view for generic unix targets,
+// so a sender's `sival_int` has to be recovered out of the pointer-sized bit pattern by hand.
+// On little-endian targets the int sits in the low-order bytes of that pattern, so it falls out
+// of a plain truncation; on big-endian targets it sits in the *high-order* bytes instead (the
+// union's low address holds the most significant byte there), so it has to be shifted down
+// first. This also covers targets where a pointer is no wider than a `c_int` (the shift is 0).
+fn extract_sival_int(raw: usize) -> c_int {
+    #[cfg(target_endian = "little")]
+    {
+        raw as u32 as c_int
+    }
+    #[cfg(target_endian = "big")]
+    {
+        (raw >> (usize::BITS - u32::BITS)) as u32 as c_int
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RawSiginfoStorage(AtomicU64);
+
+unsafe impl Exfiltrator for WithRawSiginfo {
+    type Storage = RawSiginfoStorage;
+    type Output = SigInfoValue;
+
+    fn supports_signal(&self, sig: c_int) -> bool {
+        let (min, max) = (libc::SIGRTMIN(), libc::SIGRTMAX());
+        (min..=max).contains(&sig)
+    }
+
+    fn store(&self, slot: &Self::Storage, _: c_int, info: &siginfo_t) {
+        let composed = if info.si_code == SI_QUEUE {
+            let raw = unsafe { info.si_value().sival_ptr } as usize;
+            Self::compose(extract_sival_int(raw))
+        } else {
+            // The signal was still delivered ‒ it just didn't come with a queued value. This
+            // must stay distinguishable from `EMPTY`, or `load` would report the signal as never
+            // having happened at all.
+            Self::DELIVERED_NO_VALUE
+        };
+        slot.0.store(composed, Ordering::SeqCst);
+    }
+
+    fn load(&self, slot: &Self::Storage, signal: c_int) -> Option<Self::Output> {
+        match slot.0.swap(Self::EMPTY, Ordering::SeqCst) {
+            Self::EMPTY => None,
+            Self::DELIVERED_NO_VALUE => Some(SigInfoValue {
+                signal,
+                value: None,
+            }),
+            composed => Some(SigInfoValue {
+                signal,
+                value: Some((composed as u32) as c_int),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::c_void;
+
+    #[test]
+    fn extract_sival_int_recovers_sender_value_regardless_of_endianness() {
+        // Mirrors libc's `sigval` layout exactly: `sival_int` and `sival_ptr` are both members
+        // of the same union, starting at the same address, the way a real sender and the kernel
+        // would lay it out.
+        #[repr(C)]
+        union RawSigval {
+            sival_int: c_int,
+            sival_ptr: *mut c_void,
+        }
+
+        // Zero out every byte first, then overwrite only the `c_int`-sized prefix, the same way
+        // a real `sigqueue(3)` sender only ever sets `sival_int` and leaves the rest of the
+        // union's bytes at whatever they were (here, zero).
+        let mut raw = RawSigval {
+            sival_ptr: std::ptr::null_mut(),
+        };
+        raw.sival_int = 42;
+        let as_ptr = unsafe { raw.sival_ptr };
+
+        assert_eq!(extract_sival_int(as_ptr as usize), 42);
+    }
+}
@@ -0,0 +1,255 @@
+//! A small lock-free ring buffer, meant to be used as [`Exfiltrator::Storage`][
+//! super::Exfiltrator::Storage].
+//!
+//! Many exfiltrators only ever need to remember a single value (or „no value“) between a `store`
+//! and the next `load`, which is why they get away with a plain atomic. Some, however, want to
+//! keep several values that were produced by separate deliveries of the same signal ‒ for
+//! example a count of missed deliveries, or the last few sender pids. [`Ring`] is a fixed
+//! capacity, lock-free buffer for exactly that.
+//!
+//! Unlike a plain head/tail counter pair, [`Ring`] is safe for *multiple concurrent producers*:
+//! the [`Exfiltrator::store`][super::Exfiltrator::store] contract explicitly allows the same
+//! signal to interrupt several threads at once, each calling `store` concurrently, so a
+//! combinator meant to back real `Exfiltrator` implementations has to cope with that instead of
+//! merely assuming a single producer.
+
+use std::cell::UnsafeCell;
+use std::cmp::Ordering as CmpOrdering;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Cell<T> {
+    // Tracks which „generation“ of the ring currently owns this slot; see [`Ring::push`] and
+    // [`Ring::pop`] for how it's used to hand a slot off between a producer and the consumer
+    // without a lock.
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A fixed-capacity, lock-free, multi-producer/multi-consumer ring buffer.
+///
+/// `CAP` is the number of elements the ring can hold before [`push`][Ring::push] starts
+/// rejecting new ones. Pushing is meant to happen from inside a signal handler (it's lock-free
+/// and does not allocate); popping is meant to happen from the regular `load` side, but both ends
+/// are safe to call from any number of concurrent callers.
+///
+/// This is the bounded MPMC queue design described by Dmitry Vyukov: each slot carries its own
+/// sequence number, so a producer (or consumer) only ever commits to a slot it has exclusively
+/// claimed via a compare-and-swap on the shared position counter.
+pub struct Ring<T, const CAP: usize> {
+    cells: Box<[Cell<T>]>,
+    enqueue_pos: AtomicUsize,
+    dequeue_pos: AtomicUsize,
+}
+
+// SAFETY: access to a given slot's `value` is always gated by a successful compare-and-swap on
+// `enqueue_pos`/`dequeue_pos` plus the slot's own `sequence`, so only one caller at a time ever
+// reads or writes through a given slot.
+unsafe impl<T: Send, const CAP: usize> Sync for Ring<T, CAP> {}
+
+impl<T, const CAP: usize> Ring<T, CAP> {
+    /// Creates a new, empty ring.
+    pub fn new() -> Self {
+        let cells = (0..CAP)
+            .map(|i| Cell {
+                sequence: AtomicUsize::new(i),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Ring {
+            cells,
+            enqueue_pos: AtomicUsize::new(0),
+            dequeue_pos: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pushes a value onto the ring.
+    ///
+    /// It is safe to call this from inside a signal handler, and safe to call it concurrently
+    /// from any number of producers. Returns the value back in `Err` if the ring is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let cell = &self.cells[pos % CAP];
+            let seq = cell.sequence.load(Ordering::Acquire);
+            let diff = (seq as isize).wrapping_sub(pos as isize);
+            match diff.cmp(&0) {
+                CmpOrdering::Equal => {
+                    match self.enqueue_pos.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: the compare-and-swap above is what hands this slot to us
+                            // exclusively; no other producer can have claimed it, and the
+                            // consumer won't touch it until `sequence` is published below.
+                            unsafe { (*cell.value.get()).write(value) };
+                            cell.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                            return Ok(());
+                        }
+                        Err(current) => pos = current,
+                    }
+                }
+                CmpOrdering::Less => return Err(value),
+                CmpOrdering::Greater => pos = self.enqueue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Pops the oldest pushed value off the ring, if there is one.
+    ///
+    /// It is fine to call this concurrently with [`push`][Ring::push] or with itself.
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let cell = &self.cells[pos % CAP];
+            let seq = cell.sequence.load(Ordering::Acquire);
+            let diff = (seq as isize).wrapping_sub(pos.wrapping_add(1) as isize);
+            match diff.cmp(&0) {
+                CmpOrdering::Equal => {
+                    match self.dequeue_pos.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: the compare-and-swap above is what hands this slot to us
+                            // exclusively; the value was published by a producer's `Release`
+                            // store to `sequence`, which our `Acquire` load above synchronizes
+                            // with.
+                            let value = unsafe { (*cell.value.get()).assume_init_read() };
+                            cell.sequence
+                                .store(pos.wrapping_add(CAP), Ordering::Release);
+                            return Some(value);
+                        }
+                        Err(current) => pos = current,
+                    }
+                }
+                CmpOrdering::Less => return None,
+                CmpOrdering::Greater => pos = self.dequeue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+impl<T, const CAP: usize> Default for Ring<T, CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const CAP: usize> fmt::Debug for Ring<T, CAP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ring")
+            .field("capacity", &CAP)
+            .field("enqueue_pos", &self.enqueue_pos.load(Ordering::Relaxed))
+            .field("dequeue_pos", &self.dequeue_pos.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<T, const CAP: usize> Drop for Ring<T, CAP> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn push_pop_preserves_fifo_order() {
+        let ring: Ring<u32, 4> = Ring::new();
+        ring.push(1).unwrap();
+        ring.push(2).unwrap();
+        ring.push(3).unwrap();
+        assert_eq!(ring.pop(), Some(1));
+        assert_eq!(ring.pop(), Some(2));
+        ring.push(4).unwrap();
+        assert_eq!(ring.pop(), Some(3));
+        assert_eq!(ring.pop(), Some(4));
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn push_errs_once_capacity_is_exceeded() {
+        let ring: Ring<u32, 2> = Ring::new();
+        ring.push(1).unwrap();
+        ring.push(2).unwrap();
+        assert_eq!(ring.push(3), Err(3));
+        assert_eq!(ring.pop(), Some(1));
+        ring.push(3).unwrap();
+        assert_eq!(ring.pop(), Some(2));
+        assert_eq!(ring.pop(), Some(3));
+    }
+
+    #[derive(Debug)]
+    struct DropCounter(Arc<StdAtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn drop_runs_destructors_for_unpopped_elements() {
+        let drops = Arc::new(StdAtomicUsize::new(0));
+        let ring: Ring<DropCounter, 4> = Ring::new();
+        ring.push(DropCounter(Arc::clone(&drops))).unwrap();
+        ring.push(DropCounter(Arc::clone(&drops))).unwrap();
+        ring.push(DropCounter(Arc::clone(&drops))).unwrap();
+        // One of the three is popped (and thus dropped) normally; the ring must drop the other
+        // two itself.
+        drop(ring.pop());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(ring);
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn concurrent_producers_lose_nothing() {
+        const PRODUCERS: usize = 8;
+        const PER_PRODUCER: usize = 1000;
+
+        let ring: Arc<Ring<usize, 64>> = Arc::new(Ring::new());
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|p| {
+                let ring = Arc::clone(&ring);
+                thread::spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        let value = p * PER_PRODUCER + i;
+                        let mut to_push = value;
+                        while let Err(back) = ring.push(to_push) {
+                            to_push = back;
+                            thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut seen = Vec::with_capacity(PRODUCERS * PER_PRODUCER);
+        while seen.len() < PRODUCERS * PER_PRODUCER {
+            match ring.pop() {
+                Some(value) => seen.push(value),
+                None => thread::yield_now(),
+            }
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), PRODUCERS * PER_PRODUCER);
+    }
+}
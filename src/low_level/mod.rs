@@ -0,0 +1,8 @@
+//! Low-level primitives for working with signals.
+//!
+//! This module holds small, self-contained building blocks that don't need the full
+//! [`iterator`][crate::iterator] machinery, such as a typed view of signal numbers.
+
+mod signal;
+
+pub use signal::Signal;
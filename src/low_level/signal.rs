@@ -0,0 +1,180 @@
+//! A typed view of signal numbers.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use libc::c_int;
+
+macro_rules! signals {
+    ($($(#[$attr:meta])* $variant:ident => $raw:ident),+ $(,)?) => {
+        /// A well-known POSIX signal.
+        ///
+        /// This is a typed view of the raw signal numbers (`c_int`) used everywhere else in this
+        /// crate, such as [`Origin::signal`][crate::iterator::exfiltrator::Origin::signal]. Not
+        /// every signal number a process can receive has a variant here (unknown numbers and
+        /// real-time signals don't), which is why conversion from `c_int` can fail; the raw
+        /// `c_int` remains the canonical representation and is always available.
+        #[non_exhaustive]
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+        pub enum Signal {
+            $($(#[$attr])* $variant,)+
+        }
+
+        impl Signal {
+            /// Returns the raw signal number on the current platform.
+            pub fn as_raw(self) -> c_int {
+                match self {
+                    $($(#[$attr])* Signal::$variant => libc::$raw,)+
+                }
+            }
+
+            fn name(self) -> &'static str {
+                match self {
+                    $($(#[$attr])* Signal::$variant => stringify!($raw),)+
+                }
+            }
+
+            /// Iterates over all the signals known on the current platform.
+            pub fn iter() -> impl Iterator<Item = Signal> {
+                [$($(#[$attr])* Signal::$variant,)+].iter().copied()
+            }
+        }
+
+        impl TryFrom<c_int> for Signal {
+            type Error = TryFromSignalError;
+
+            fn try_from(raw: c_int) -> Result<Self, Self::Error> {
+                match raw {
+                    $($(#[$attr])* libc::$raw => Ok(Signal::$variant),)+
+                    _ => Err(TryFromSignalError(raw)),
+                }
+            }
+        }
+
+        impl FromStr for Signal {
+            type Err = ParseSignalError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $(
+                    $(#[$attr])*
+                    {
+                        let name = stringify!($raw);
+                        if s.eq_ignore_ascii_case(name) || s.eq_ignore_ascii_case(&name[3..]) {
+                            return Ok(Signal::$variant);
+                        }
+                    }
+                )+
+                Err(ParseSignalError(s.to_owned()))
+            }
+        }
+    };
+}
+
+signals! {
+    Hup => SIGHUP,
+    Int => SIGINT,
+    Quit => SIGQUIT,
+    Ill => SIGILL,
+    Trap => SIGTRAP,
+    Abrt => SIGABRT,
+    Bus => SIGBUS,
+    Fpe => SIGFPE,
+    Kill => SIGKILL,
+    Usr1 => SIGUSR1,
+    Segv => SIGSEGV,
+    Usr2 => SIGUSR2,
+    Pipe => SIGPIPE,
+    Alrm => SIGALRM,
+    Term => SIGTERM,
+    #[cfg(target_os = "linux")]
+    Stkflt => SIGSTKFLT,
+    Chld => SIGCHLD,
+    Cont => SIGCONT,
+    Stop => SIGSTOP,
+    Tstp => SIGTSTP,
+    Ttin => SIGTTIN,
+    Ttou => SIGTTOU,
+    Urg => SIGURG,
+    Xcpu => SIGXCPU,
+    Xfsz => SIGXFSZ,
+    Vtalrm => SIGVTALRM,
+    Prof => SIGPROF,
+    Winch => SIGWINCH,
+    Io => SIGIO,
+    #[cfg(target_os = "linux")]
+    Pwr => SIGPWR,
+    Sys => SIGSYS,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when a raw signal number doesn't correspond to a known [`Signal`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TryFromSignalError(c_int);
+
+impl fmt::Display for TryFromSignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown signal number {}", self.0)
+    }
+}
+
+impl std::error::Error for TryFromSignalError {}
+
+/// The error returned when a string doesn't name a known [`Signal`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseSignalError(String);
+
+impl fmt::Display for ParseSignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown signal name {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseSignalError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_round_trips_through_as_raw() {
+        for signal in Signal::iter() {
+            assert_eq!(Signal::try_from(signal.as_raw()).unwrap(), signal);
+        }
+    }
+
+    #[test]
+    fn try_from_unknown_number_errs() {
+        let err = Signal::try_from(c_int::MAX).unwrap_err();
+        assert_eq!(err, TryFromSignalError(c_int::MAX));
+        assert_eq!(err.to_string(), format!("unknown signal number {}", c_int::MAX));
+    }
+
+    #[test]
+    fn from_str_round_trips_through_display() {
+        for signal in Signal::iter() {
+            assert_eq!(signal.to_string().parse::<Signal>().unwrap(), signal);
+        }
+    }
+
+    #[test]
+    fn from_str_accepts_name_without_sig_prefix() {
+        assert_eq!("INT".parse::<Signal>().unwrap(), Signal::Int);
+        assert_eq!("int".parse::<Signal>().unwrap(), Signal::Int);
+    }
+
+    #[test]
+    fn from_str_unknown_name_errs() {
+        let err = "NOSUCHSIGNAL".parse::<Signal>().unwrap_err();
+        assert_eq!(err, ParseSignalError("NOSUCHSIGNAL".to_owned()));
+        assert_eq!(
+            err.to_string(),
+            "unknown signal name \"NOSUCHSIGNAL\"".to_owned(),
+        );
+    }
+}